@@ -6,14 +6,26 @@ use crate::{
 };
 
 use snarkos_models::{
-    curves::{Field, Group, PrimeField},
+    curves::{AffineCurve, Field, Group, PrimeField, ProjectiveCurve},
     gadgets::utilities::{
         boolean::Boolean, uint128::UInt128, uint16::UInt16, uint32::UInt32, uint64::UInt64,
         uint8::UInt8,
     },
 };
+use serde::{Deserialize, Serialize};
 use std::fmt;
 
+/// A wire format for `ConstrainedValue`, written to `outputs/` and read from `inputs/`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum SerializedConstrainedValue {
+    Integer(IntegerType, String),
+    FieldElement(String),
+    GroupElement(String),
+    Boolean(bool),
+    Array(Vec<SerializedConstrainedValue>),
+    CircuitExpression(String, Vec<(String, SerializedConstrainedValue)>),
+}
+
 #[derive(Clone, PartialEq, Eq)]
 pub struct ConstrainedCircuitMember<F: Field + PrimeField, G: Group>(
     pub Identifier<F, G>,
@@ -40,8 +52,12 @@ pub enum ConstrainedValue<F: Field + PrimeField, G: Group> {
     Unresolved(String),
 }
 
-impl<F: Field + PrimeField, G: Group> ConstrainedValue<F, G> {
-    pub(crate) fn expect_type(&self, _type: &Type<F, G>) -> Result<(), ValueError> {
+impl<F: Field + PrimeField, G: Group + ProjectiveCurve> ConstrainedValue<F, G> {
+    pub(crate) fn expect_type(
+        &self,
+        _type: &Type<F, G>,
+        self_circuit_name: Option<&Identifier<F, G>>,
+    ) -> Result<(), ValueError> {
         match (self, _type) {
             (ConstrainedValue::Integer(ref integer), Type::IntegerType(ref _type)) => {
                 integer.expect_type(_type)?;
@@ -63,7 +79,7 @@ impl<F: Field + PrimeField, G: Group> ConstrainedValue<F, G> {
 
                 // check each value in array matches
                 for value in arr {
-                    value.expect_type(&next_type)?;
+                    value.expect_type(&next_type, self_circuit_name)?;
                 }
             }
             (
@@ -81,23 +97,27 @@ impl<F: Field + PrimeField, G: Group> ConstrainedValue<F, G> {
                 ConstrainedValue::CircuitExpression(ref actual_name, ref _members),
                 Type::SelfType,
             ) => {
-                if Identifier::new("Self".into()) == *actual_name {
+                // `Self` resolves to either the literal `Self` identifier or the circuit currently being checked
+                let self_identifier = Identifier::new("Self".into());
+                let expected_name = self_circuit_name.unwrap_or(&self_identifier);
+
+                if *actual_name != self_identifier && actual_name != expected_name {
                     return Err(ValueError::CircuitName(
-                        "Self".into(),
+                        expected_name.to_string(),
                         actual_name.to_string(),
                     ));
                 }
             }
             (ConstrainedValue::Return(ref values), _type) => {
                 for value in values {
-                    value.expect_type(_type)?;
+                    value.expect_type(_type, self_circuit_name)?;
                 }
             }
             (ConstrainedValue::Mutable(ref value), _type) => {
-                value.expect_type(&_type)?;
+                value.expect_type(&_type, self_circuit_name)?;
             }
             (ConstrainedValue::Static(ref value), _type) => {
-                value.expect_type(&_type)?;
+                value.expect_type(&_type, self_circuit_name)?;
             }
             (value, _type) => {
                 return Err(ValueError::TypeError(format!(
@@ -110,15 +130,205 @@ impl<F: Field + PrimeField, G: Group> ConstrainedValue<F, G> {
         Ok(())
     }
 
+    pub(crate) fn to_serializable(&self) -> Result<SerializedConstrainedValue, ValueError> {
+        Ok(match self {
+            ConstrainedValue::Integer(integer) => {
+                SerializedConstrainedValue::Integer(integer.get_type(), integer.to_string())
+            }
+            ConstrainedValue::FieldElement(field) => {
+                SerializedConstrainedValue::FieldElement(field.to_string())
+            }
+            ConstrainedValue::GroupElement(group) => {
+                SerializedConstrainedValue::GroupElement(Self::group_to_string(group))
+            }
+            ConstrainedValue::Boolean(bool) => {
+                SerializedConstrainedValue::Boolean(bool.get_value().unwrap_or_default())
+            }
+            ConstrainedValue::Array(values) => SerializedConstrainedValue::Array(
+                values
+                    .iter()
+                    .map(|value| value.to_serializable())
+                    .collect::<Result<Vec<_>, _>>()?,
+            ),
+            ConstrainedValue::CircuitExpression(identifier, members) => {
+                let members = members
+                    .iter()
+                    .map(|member| Ok((member.0.to_string(), member.1.to_serializable()?)))
+                    .collect::<Result<Vec<_>, ValueError>>()?;
+
+                SerializedConstrainedValue::CircuitExpression(identifier.to_string(), members)
+            }
+            ConstrainedValue::Mutable(value) | ConstrainedValue::Static(value) => {
+                value.to_serializable()?
+            }
+            value => {
+                return Err(ValueError::Serialization(format!(
+                    "cannot serialize value {}",
+                    value
+                )))
+            }
+        })
+    }
+
+    pub(crate) fn from_serializable(
+        serialized: SerializedConstrainedValue,
+        expected: &Type<F, G>,
+    ) -> Result<Self, ValueError> {
+        Ok(match serialized {
+            SerializedConstrainedValue::Integer(integer_type, value) => {
+                ConstrainedValue::from_type(value, &Type::IntegerType(integer_type))?
+            }
+            SerializedConstrainedValue::FieldElement(value) => {
+                ConstrainedValue::from_type(value, &Type::FieldElement)?
+            }
+            SerializedConstrainedValue::GroupElement(value) => {
+                ConstrainedValue::from_type(value, &Type::GroupElement)?
+            }
+            SerializedConstrainedValue::Boolean(value) => {
+                ConstrainedValue::Boolean(Boolean::Constant(value))
+            }
+            SerializedConstrainedValue::Array(values) => match expected {
+                Type::Array(element_type, dimensions) => {
+                    let next_type = element_type.next_dimension(dimensions);
+
+                    ConstrainedValue::Array(
+                        values
+                            .into_iter()
+                            .map(|value| ConstrainedValue::from_serializable(value, &next_type))
+                            .collect::<Result<Vec<_>, _>>()?,
+                    )
+                }
+                _type => {
+                    return Err(ValueError::TypeError(format!(
+                        "expected array type, got {}",
+                        _type
+                    )))
+                }
+            },
+            SerializedConstrainedValue::CircuitExpression(name, _members) => {
+                return Err(ValueError::Serialization(format!(
+                    "deserializing circuit expression {} as a program input is not supported",
+                    name
+                )));
+            }
+        })
+    }
+
     pub(crate) fn from_other(
         value: String,
         other: &ConstrainedValue<F, G>,
     ) -> Result<Self, ValueError> {
-        let other_type = other.to_type();
+        let other_type = other.to_type()?;
 
         ConstrainedValue::from_type(value, &other_type)
     }
 
+    // formats a group element as an affine `(x, y)` literal, so that it round-trips
+    // through `group_from_str` rather than relying on `G`'s own `Display` impl
+    fn group_to_string(group: &G) -> String {
+        let affine = group.into_affine();
+
+        format!("({}, {})", affine.x, affine.y)
+    }
+
+    // accepts either a bare scalar string (multiplied against the default
+    // generator) or an affine `(x, y)` literal, rejected unless it is on the curve
+    // and in the prime-order subgroup
+    fn group_from_str(value: &str) -> Result<G, ValueError> {
+        use std::str::FromStr;
+
+        let value = value.trim();
+
+        let invalid = || ValueError::InvalidGroupElement(value.to_string());
+
+        if value.starts_with('(') && value.ends_with(')') {
+            let mut coordinates = value[1..value.len() - 1].splitn(2, ',');
+
+            let x = coordinates.next().ok_or_else(invalid)?.trim();
+            let y = coordinates.next().ok_or_else(invalid)?.trim();
+
+            let x = <G::Affine as AffineCurve>::BaseField::from_str(x).map_err(|_| invalid())?;
+            let y = <G::Affine as AffineCurve>::BaseField::from_str(y).map_err(|_| invalid())?;
+
+            let affine = G::Affine::from_coordinates(x, y).ok_or_else(invalid)?;
+
+            if !affine.is_on_curve() || !affine.is_in_correct_subgroup_assuming_on_curve() {
+                return Err(invalid());
+            }
+
+            return Ok(affine.into_projective());
+        }
+
+        let scalar = G::ScalarField::from_str(value).map_err(|_| invalid())?;
+
+        Ok(G::default().mul(&scalar))
+    }
+
+    pub(crate) fn group_add(&self, other: &Self) -> Result<Self, ValueError> {
+        match self {
+            ConstrainedValue::Mutable(value) => return value.group_add(other),
+            ConstrainedValue::Static(value) => return value.group_add(other),
+            _ => {}
+        }
+
+        match other {
+            ConstrainedValue::Mutable(value) => return self.group_add(value),
+            ConstrainedValue::Static(value) => return self.group_add(value),
+            _ => {}
+        }
+
+        match (self, other) {
+            (ConstrainedValue::GroupElement(a), ConstrainedValue::GroupElement(b)) => {
+                Ok(ConstrainedValue::GroupElement(*a + *b))
+            }
+            (a, b) => Err(ValueError::IncompatibleTypes(format!(
+                "cannot add {} and {}",
+                a, b
+            ))),
+        }
+    }
+
+    pub(crate) fn group_mul(&self, scalar: &Self) -> Result<Self, ValueError> {
+        use std::str::FromStr;
+
+        match self {
+            ConstrainedValue::Mutable(value) => return value.group_mul(scalar),
+            ConstrainedValue::Static(value) => return value.group_mul(scalar),
+            _ => {}
+        }
+
+        match scalar {
+            ConstrainedValue::Mutable(value) => return self.group_mul(value),
+            ConstrainedValue::Static(value) => return self.group_mul(value),
+            _ => {}
+        }
+
+        let group = match self {
+            ConstrainedValue::GroupElement(group) => group,
+            value => {
+                return Err(ValueError::IncompatibleTypes(format!(
+                    "expected group element, got {}",
+                    value
+                )))
+            }
+        };
+
+        let scalar_string = match scalar {
+            ConstrainedValue::FieldElement(field) => field.to_string(),
+            ConstrainedValue::Integer(integer) => integer.to_string(),
+            value => {
+                return Err(ValueError::IncompatibleTypes(format!(
+                    "cannot multiply a group element by {}",
+                    value
+                )))
+            }
+        };
+
+        let scalar = G::ScalarField::from_str(&scalar_string).unwrap_or_default();
+
+        Ok(ConstrainedValue::GroupElement(group.mul(&scalar)))
+    }
+
     pub(crate) fn from_type(value: String, _type: &Type<F, G>) -> Result<Self, ValueError> {
         Ok(match _type {
             Type::IntegerType(integer_type) => ConstrainedValue::Integer(match integer_type {
@@ -131,26 +341,99 @@ impl<F: Field + PrimeField, G: Group> ConstrainedValue<F, G> {
             Type::FieldElement => ConstrainedValue::FieldElement(FieldElement::Constant(
                 F::from_str(&value).unwrap_or_default(),
             )),
-            Type::GroupElement => ConstrainedValue::GroupElement({
-                use std::str::FromStr;
-
-                let scalar = G::ScalarField::from_str(&value).unwrap_or_default();
-                let point = G::default().mul(&scalar);
-                point
-            }),
+            Type::GroupElement => ConstrainedValue::GroupElement(Self::group_from_str(&value)?),
             Type::Boolean => ConstrainedValue::Boolean(Boolean::Constant(value.parse::<bool>()?)),
             _ => ConstrainedValue::Unresolved(value),
         })
     }
 
-    pub(crate) fn to_type(&self) -> Type<F, G> {
-        match self {
+    fn integer_type_bits(integer_type: &IntegerType) -> u32 {
+        match integer_type {
+            IntegerType::U8 => 8,
+            IntegerType::U16 => 16,
+            IntegerType::U32 => 32,
+            IntegerType::U64 => 64,
+            IntegerType::U128 => 128,
+        }
+    }
+
+    pub(crate) fn resolve(&self, expected: &Type<F, G>) -> Result<Self, ValueError> {
+        Ok(match self {
+            ConstrainedValue::Unresolved(value) => {
+                ConstrainedValue::from_type(value.clone(), expected)?
+            }
+            ConstrainedValue::Integer(integer) => match expected {
+                Type::IntegerType(expected_type) if integer.expect_type(expected_type).is_err() => {
+                    // only widen an integer of ambiguous origin to a larger type;
+                    // narrowing (or any other mismatch) is a genuine type error,
+                    // not something `resolve` should silently paper over
+                    if Self::integer_type_bits(expected_type) <= Self::integer_type_bits(&integer.get_type())
+                    {
+                        return Err(ValueError::TypeError(format!(
+                            "expected type {}, got {}",
+                            expected, self
+                        )));
+                    }
+
+                    ConstrainedValue::from_type(integer.to_string(), expected)?
+                }
+                _ => self.clone(),
+            },
+            ConstrainedValue::Array(values) => match expected {
+                Type::Array(element_type, dimensions) => {
+                    let next_type = element_type.next_dimension(dimensions);
+
+                    ConstrainedValue::Array(
+                        values
+                            .iter()
+                            .map(|value| value.resolve(&next_type))
+                            .collect::<Result<Vec<_>, _>>()?,
+                    )
+                }
+                _ => self.clone(),
+            },
+            ConstrainedValue::Mutable(value) => {
+                ConstrainedValue::Mutable(Box::new(value.resolve(expected)?))
+            }
+            ConstrainedValue::Static(value) => {
+                ConstrainedValue::Static(Box::new(value.resolve(expected)?))
+            }
+            value => value.clone(),
+        })
+    }
+
+    pub(crate) fn to_type(&self) -> Result<Type<F, G>, ValueError> {
+        Ok(match self {
             ConstrainedValue::Integer(integer) => Type::IntegerType(integer.get_type()),
             ConstrainedValue::FieldElement(_field) => Type::FieldElement,
             ConstrainedValue::GroupElement(_group) => Type::GroupElement,
             ConstrainedValue::Boolean(_bool) => Type::Boolean,
-            _ => unimplemented!("to type only implemented for primitives"),
-        }
+            ConstrainedValue::Array(values) => {
+                if values.is_empty() {
+                    return Err(ValueError::ArrayLength(
+                        "cannot infer the type of an empty array".into(),
+                    ));
+                }
+
+                // infer the element type from the first value, accumulating dimensions
+                // outward so a nested array collapses to a single flat dimension list
+                let (element_type, mut inner_dimensions) = match values[0].to_type()? {
+                    Type::Array(element_type, inner_dimensions) => (*element_type, inner_dimensions),
+                    element_type => (element_type, vec![]),
+                };
+
+                let mut dimensions = vec![values.len()];
+                dimensions.append(&mut inner_dimensions);
+
+                Type::Array(Box::new(element_type), dimensions)
+            }
+            ConstrainedValue::CircuitExpression(identifier, _members) => {
+                Type::Circuit(identifier.clone())
+            }
+            ConstrainedValue::Mutable(value) => value.to_type()?,
+            ConstrainedValue::Static(value) => value.to_type()?,
+            _ => unimplemented!("to type only implemented for primitives, arrays, and circuit expressions"),
+        })
     }
 }
 
@@ -209,3 +492,45 @@ impl<F: Field + PrimeField, G: Group> fmt::Debug for ConstrainedValue<F, G> {
         write!(f, "{}", self)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use snarkos_curves::edwards_bls12::{EdwardsProjective, Fq};
+
+    type TestValue = ConstrainedValue<Fq, EdwardsProjective>;
+
+    #[test]
+    fn group_element_round_trips_through_serialization() {
+        let group = TestValue::group_from_str("5").unwrap();
+        let value = TestValue::GroupElement(group);
+
+        let serialized = value.to_serializable().unwrap();
+        let deserialized = TestValue::from_serializable(serialized, &Type::GroupElement).unwrap();
+
+        assert_eq!(value, deserialized);
+    }
+
+    // an untyped literal like `let b = 1` defaults to u32 until resolved; `resolve`
+    // should widen it to a larger expected type but never silently narrow it
+    #[test]
+    fn resolve_widens_an_ambiguous_integer_to_a_larger_type() {
+        let value = TestValue::Integer(Integer::U32(UInt32::constant(5)));
+
+        let resolved = value
+            .resolve(&Type::IntegerType(IntegerType::U64))
+            .unwrap();
+
+        assert_eq!(
+            resolved,
+            TestValue::Integer(Integer::U64(UInt64::constant(5)))
+        );
+    }
+
+    #[test]
+    fn resolve_rejects_narrowing_an_integer_to_a_smaller_type() {
+        let value = TestValue::Integer(Integer::U32(UInt32::constant(5)));
+
+        assert!(value.resolve(&Type::IntegerType(IntegerType::U8)).is_err());
+    }
+}